@@ -1,16 +1,20 @@
 //! A crate for stack-allocated fixed-length multiqueues. A multiqueue is an array of a given number of queues,
 //! each able to be accessed independently.
 //!
-//! In term, this crate should include a feature that enables the user to specify what the multiqueue must do
-//! in the case the `pop` or `push` method cannot operate (e.g. empty or full individual queue.).
-//! For instance, one could wish the operation is, in such a case, applied to the following queue.
+//! [`MultiStackQueue::push_with_policy`] lets the caller specify what the multiqueue must do
+//! in the case the `push` method cannot operate because the target queue is full, via the
+//! [`OverflowPolicy`] enum. For instance, [`OverflowPolicy::CascadeDown`] makes the push fall
+//! through to the next queue.
 //!
 //! This crate was motivated by the creation of a multiple-round-robin-based scheduler in a toy micro-kernel.
 //! Each queue holds all the threads within the same priority level.
 //! Attempting to create a new thread in an already full priority level would simply decrease its priority
 //! until a suitable non-full queue is found.
 
-
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU16, AtomicU8, AtomicUsize, Ordering};
 
 /// Errors that may be encountered during use of the [`MultiStackQueue`]
 ///
@@ -26,18 +30,102 @@ pub enum MSQError {
     UnknowmError,
 }
 
-/// An abstract structure containin multiple stack-allocated bounded queues.
+/// Policy applied by [`MultiStackQueue::push_with_policy`] when the target queue is full.
 ///
-/// Each queue is stored as an `[Option<T>; N]` and the multiqueue stores
-//// the complete data in an `[[Option<T>; N]; M].
+/// * `Fail` - Behaves like [`MultiStackQueue::push`]: returns `QueueFull` immediately.
+/// * `CascadeDown` - Retries at `id + 1`, `id + 2`, ... up to `M - 1`, landing in the first
+///   non-full queue found. Models "decrease priority until a non-full queue is found".
+/// * `CascadeUp` - Symmetric to `CascadeDown`, retrying at `id - 1`, `id - 2`, ... down to `0`.
+/// * `Wrap` - Retries every other queue starting at `id + 1` and wrapping around through `0`,
+///   stopping once every queue has been tried.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    Fail,
+    CascadeUp,
+    CascadeDown,
+    Wrap,
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for usize {}
+}
+
+/// Width of the head/tail counters backing a [`MultiStackQueue`], following `heapless`'s
+/// `spsc::Queue` index generic: a multiqueue with many small queues (`M` large, `N` small)
+/// doesn't have to pay for `usize`-sized bookkeeping on every one of them.
+///
+/// Sealed; only `u8`, `u16` and `usize` implement it. Use [`MultiStackQueue::new_u8`] /
+/// [`MultiStackQueue::new_u16`] rather than naming this trait directly.
+pub trait IndexWidth: sealed::Sealed {
+    /// Largest value this width's atomic counter can hold.
+    const MAX: usize;
+    #[doc(hidden)]
+    type Atomic: Send + Sync;
+    #[doc(hidden)]
+    fn new_atomic(value: usize) -> Self::Atomic;
+    #[doc(hidden)]
+    fn load(atomic: &Self::Atomic, order: Ordering) -> usize;
+    #[doc(hidden)]
+    fn store(atomic: &Self::Atomic, value: usize, order: Ordering);
+}
+
+impl IndexWidth for u8 {
+    const MAX: usize = u8::MAX as usize;
+    type Atomic = AtomicU8;
+    fn new_atomic(value: usize) -> AtomicU8 {
+        AtomicU8::new(value as u8)
+    }
+    fn load(atomic: &AtomicU8, order: Ordering) -> usize {
+        atomic.load(order) as usize
+    }
+    fn store(atomic: &AtomicU8, value: usize, order: Ordering) {
+        atomic.store(value as u8, order)
+    }
+}
+
+impl IndexWidth for u16 {
+    const MAX: usize = u16::MAX as usize;
+    type Atomic = AtomicU16;
+    fn new_atomic(value: usize) -> AtomicU16 {
+        AtomicU16::new(value as u16)
+    }
+    fn load(atomic: &AtomicU16, order: Ordering) -> usize {
+        atomic.load(order) as usize
+    }
+    fn store(atomic: &AtomicU16, value: usize, order: Ordering) {
+        atomic.store(value as u16, order)
+    }
+}
 
+impl IndexWidth for usize {
+    const MAX: usize = usize::MAX;
+    type Atomic = AtomicUsize;
+    fn new_atomic(value: usize) -> AtomicUsize {
+        AtomicUsize::new(value)
+    }
+    fn load(atomic: &AtomicUsize, order: Ordering) -> usize {
+        atomic.load(order)
+    }
+    fn store(atomic: &AtomicUsize, value: usize, order: Ordering) {
+        atomic.store(value, order)
+    }
+}
+
+/// An abstract structure containin multiple stack-allocated bounded queues.
+///
+/// Each queue is stored as an `[MaybeUninit<T>; N]` and the multiqueue stores
+/// the complete data in an `[[MaybeUninit<T>; N]; M]`, so `T` needs neither `Copy` nor
+/// `Default` to be queued.
 ///
 /// # Usage
 ///
 /// The generic definition is the following :
 ///
 /// ```ignore
-/// MultiStackQueue<T, const N: usize, const M: usize>
+/// MultiStackQueue<T, const N: usize, const M: usize, I = usize>
 /// ```
 ///
 /// With :
@@ -45,6 +133,9 @@ pub enum MSQError {
 /// * `T` - type contained in the queues
 /// * `N` - length of each queue
 /// * `M` - number of queues
+/// * `I` - [`IndexWidth`] used to store each queue's head/tail counters, `usize` by default.
+///   Use `u8` or `u16` (via [`MultiStackQueue::new_u8`] / [`MultiStackQueue::new_u16`]) to shrink
+///   the multiqueue's metadata footprint when `M` is large and `N` is small.
 ///
 /// # Example usecases
 ///
@@ -60,7 +151,7 @@ pub enum MSQError {
 /// #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// struct TestStruct {
 ///     a: usize,
-///     b: bool,   
+///     b: bool,
 /// }
 ///
 /// let mut msq: MultiStackQueue<TestStruct, 16, 8> = MultiStackQueue::new();
@@ -71,22 +162,134 @@ pub enum MSQError {
 /// assert_eq!(msq.pop(7).unwrap(), value);
 /// ```
 ///
-/// # Roadmap
+pub struct MultiStackQueue<T, const N: usize, const M: usize, I: IndexWidth = usize> {
+    data: UnsafeCell<[[MaybeUninit<T>; N]; M]>,
+    heads: [I::Atomic; M],
+    tails: [I::Atomic; M],
+    /// Last queue id served by [`MultiStackQueue::pop_round_robin`], so the next call resumes
+    /// after it instead of always favoring low-numbered queues.
+    rr_cursor: usize,
+}
+
+/// Appends `value` to the queue `id`, returning `QueueFull` if there is no room left.
 ///
-/// Using arrays of `Option<T>` requires that `T` implements the `Copy` trait, which may not be the case.
-/// A different approach is to use default values instead of `Option::None` to initialize the arrays.
-/// This way, `T` must need not implement `Copy` but `Default`, which may be beneficial in some usecases.
+/// `head` and `tail` are counters in `0..2*N` (a "lap" scheme): the actual storage slot is
+/// `tail % N`, and the number of live elements is `(tail + 2*N - head) % (2*N)`, which is `0`
+/// when empty and `N` when full. Keeping both counters within a fixed `0..2*N` range (rather
+/// than letting them grow without bound) is what makes it possible to store them in a
+/// narrower-than-`usize` [`IndexWidth`].
 ///
-/// Another idea would be to make use of the `MaybeUnInit` type.
+/// On failure, hands `value` back to the caller so it can be retried elsewhere (e.g. at another
+/// queue, as [`MultiStackQueue::push_with_policy`] does) instead of being silently dropped.
+fn enqueue_at<T, I: IndexWidth, const N: usize, const M: usize>(
+    data: *mut [[MaybeUninit<T>; N]; M],
+    head: &I::Atomic,
+    tail: &I::Atomic,
+    id: usize,
+    value: T,
+) -> Result<(), (MSQError, T)> {
+    let lap = 2 * N;
+    let t = I::load(tail, Ordering::Relaxed);
+    let h = I::load(head, Ordering::Acquire);
+    if (t + lap - h) % lap == N {
+        return Err((MSQError::QueueFull, value));
+    }
+    // SAFETY: a slot may only be written here once it has been vacated (observed via the
+    // acquire load of `head` above), so a concurrent reader of that same slot can never
+    // observe a torn write.
+    unsafe {
+        (*data)[id][t % N].write(value);
+    }
+    I::store(tail, (t + 1) % lap, Ordering::Release);
+    Ok(())
+}
+
+/// Removes and returns the front value of queue `id`, returning `QueueEmpty` if there is none.
 ///
-pub struct MultiStackQueue<T, const N: usize, const M: usize> {
-    data: [[Option<T>; N]; M],
-    ins: [usize; M],
-    outs: [usize; M],
-    empty: [bool; M],
+/// See [`enqueue_at`] for the counter convention used by `head`/`tail`.
+fn dequeue_at<T, I: IndexWidth, const N: usize, const M: usize>(
+    data: *mut [[MaybeUninit<T>; N]; M],
+    head: &I::Atomic,
+    tail: &I::Atomic,
+    id: usize,
+) -> Result<T, MSQError> {
+    let lap = 2 * N;
+    let h = I::load(head, Ordering::Relaxed);
+    let t = I::load(tail, Ordering::Acquire);
+    if (t + lap - h) % lap == 0 {
+        return Err(MSQError::QueueEmpty);
+    }
+    // SAFETY: a slot may only be read here once it has been published (observed via the
+    // acquire load of `tail` above), so the write from `enqueue_at` has fully happened-before.
+    // The slot is logically moved out here; `head` is advanced immediately after so the Drop
+    // impl, which only visits the still-live `head..tail` range, never revisits it.
+    let res = unsafe { (*data)[id][h % N].assume_init_read() };
+    I::store(head, (h + 1) % lap, Ordering::Release);
+    Ok(res)
+}
+
+/// Allocation-free iterator over the queue indices [`MultiStackQueue::push_with_policy`]
+/// should try for a given `id`, in order, as dictated by an [`OverflowPolicy`].
+enum OverflowCandidates {
+    Fail(Option<usize>),
+    CascadeDown { next: usize, m: usize },
+    CascadeUp { next: Option<usize> },
+    Wrap { next: usize, m: usize, id: usize, wrapped: bool },
+}
+
+impl OverflowCandidates {
+    fn new(id: usize, m: usize, policy: OverflowPolicy) -> Self {
+        match policy {
+            OverflowPolicy::Fail => OverflowCandidates::Fail(Some(id)),
+            OverflowPolicy::CascadeDown => OverflowCandidates::CascadeDown { next: id, m },
+            OverflowPolicy::CascadeUp => OverflowCandidates::CascadeUp { next: Some(id) },
+            OverflowPolicy::Wrap => OverflowCandidates::Wrap { next: id, m, id, wrapped: false },
+        }
+    }
 }
 
-impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
+impl Iterator for OverflowCandidates {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            OverflowCandidates::Fail(next) => next.take(),
+            OverflowCandidates::CascadeDown { next, m } => {
+                if *next >= *m {
+                    return None;
+                }
+                let candidate = *next;
+                *next += 1;
+                Some(candidate)
+            }
+            OverflowCandidates::CascadeUp { next } => {
+                let candidate = (*next)?;
+                *next = candidate.checked_sub(1);
+                Some(candidate)
+            }
+            OverflowCandidates::Wrap { next, m, id, wrapped } => {
+                if !*wrapped && *next >= *m {
+                    *wrapped = true;
+                    *next = 0;
+                }
+                if *wrapped && *next >= *id {
+                    return None;
+                }
+                let candidate = *next;
+                *next += 1;
+                Some(candidate)
+            }
+        }
+    }
+}
+
+impl<T, const N: usize, const M: usize, I: IndexWidth> MultiStackQueue<T, N, M, I> {
+    /// Compile-time check that `N` fits `I`'s lap counter (`head`/`tail` range over `0..2*N`).
+    /// Referenced from `new` so it is evaluated at monomorphization time.
+    const INDEX_WIDTH_FITS_N: () = assert!(
+        N <= I::MAX / 2 + 1,
+        "N is too large for this MultiStackQueue's index width; shrink N or use a wider one (e.g. new_u16 or new)"
+    );
+
     /// Returns a new empty multiqueue.
     ///
     /// # Examples
@@ -96,10 +299,9 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
     /// // Returns a fresh empty multiqueue containing 8 queues of `usize` with size 16
     /// let a: MultiStackQueue<usize, 16, 8> = MultiStackQueue::new();
     ///
-    /// #[derive(Clone, Copy)]
     /// struct TestStruct {
     ///     a: usize,
-    ///     b: bool    
+    ///     b: bool
     /// }
     ///
     /// let random_data = TestStruct { a: 42, b: false };
@@ -108,11 +310,14 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
     /// ```
     ///
     pub fn new() -> Self {
+        let () = Self::INDEX_WIDTH_FITS_N;
         MultiStackQueue {
-            data: [[None; N]; M],
-            ins: [0usize; M],
-            outs: [0usize; M],
-            empty: [true; M],
+            data: UnsafeCell::new(core::array::from_fn(|_| {
+                core::array::from_fn(|_| MaybeUninit::uninit())
+            })),
+            heads: core::array::from_fn(|_| I::new_atomic(0)),
+            tails: core::array::from_fn(|_| I::new_atomic(0)),
+            rr_cursor: 0,
         }
     }
     /// Appends a value to the multiqueue.
@@ -125,7 +330,7 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
     /// #[derive(Clone, Copy)]
     /// struct TestStruct {
     ///     a: usize,
-    ///     b: bool    
+    ///     b: bool
     /// }
     ///
     /// let random_data = TestStruct { a: 42, b: false };
@@ -139,18 +344,56 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
         if id >= M {
             return Err(MSQError::QueueIndexOutOfBounds);
         }
-        self.try_and_push(id, value)
+        enqueue_at::<T, I, N, M>(self.data.get(), &self.heads[id], &self.tails[id], id, value)
+            .map_err(|(e, _)| e)
     }
-    fn try_and_push(&mut self, id: usize, value: T) -> Result<(), MSQError> {
-        if self.ins[id] == self.outs[id] && !self.empty[id] {
-            // Queue is full
-            Err(MSQError::QueueFull)
-        } else {
-            self.data[id][self.ins[id]] = Some(value);
-            self.ins[id] = (self.ins[id] + 1) % N;
-            self.empty[id] = false;
-            Ok(())
+    /// Appends a value to the multiqueue, applying `policy` when queue `id` is full.
+    ///
+    /// Returns the index of the queue the value actually landed in, which may differ from `id`
+    /// when `policy` cascades to another queue. Returns `QueueFull` only once every candidate
+    /// queue allowed by `policy` has been tried and found full.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::{MultiStackQueue, OverflowPolicy};
+    ///
+    /// let mut msq: MultiStackQueue<usize, 1, 4> = MultiStackQueue::new();
+    /// msq.push(0, 1).unwrap();
+    /// // Queue 0 is full, so the value cascades down into queue 1.
+    /// let landed = msq.push_with_policy(0, 2, OverflowPolicy::CascadeDown).unwrap();
+    /// assert_eq!(landed, 1);
+    /// ```
+    ///
+    pub fn push_with_policy(
+        &mut self,
+        id: usize,
+        value: T,
+        policy: OverflowPolicy,
+    ) -> Result<usize, MSQError> {
+        if id >= M {
+            return Err(MSQError::QueueIndexOutOfBounds);
         }
+        let mut value = value;
+        for candidate in self.overflow_candidates(id, policy) {
+            match enqueue_at::<T, I, N, M>(
+                self.data.get(),
+                &self.heads[candidate],
+                &self.tails[candidate],
+                candidate,
+                value,
+            ) {
+                Ok(()) => return Ok(candidate),
+                Err((MSQError::QueueFull, v)) => value = v,
+                Err((e, _)) => return Err(e),
+            }
+        }
+        Err(MSQError::QueueFull)
+    }
+    /// Returns the ordered sequence of queue indices `push_with_policy` should try for `id`
+    /// under `policy`, starting with `id` itself.
+    fn overflow_candidates(&self, id: usize, policy: OverflowPolicy) -> OverflowCandidates {
+        OverflowCandidates::new(id, M, policy)
     }
     /// Pops a value from the multiqueue.
     ///
@@ -162,7 +405,7 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
     /// #[derive(Clone, Copy)]
     /// struct TestStruct {
     ///     a: usize,
-    ///     b: bool    
+    ///     b: bool
     /// }
     ///
     /// let random_data = TestStruct { a: 42, b: false };
@@ -177,20 +420,66 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
         if id >= M {
             return Err(MSQError::QueueIndexOutOfBounds);
         }
-        self.try_and_pop(id)
-    }
-    fn try_and_pop(&mut self, id: usize) -> Result<T, MSQError> {
-        if self.empty[id] {
-            Err(MSQError::QueueEmpty)
-        } else {
-            // TODO The unwrap is not ideal
-            let res = self.data[id][self.outs[id]].take().unwrap();
-            self.outs[id] = (self.outs[id] + 1) % N;
-            if self.outs[id] == self.ins[id] {
-                self.empty[id] = true;
+        dequeue_at::<T, I, N, M>(self.data.get(), &self.heads[id], &self.tails[id], id)
+    }
+    /// Treats the `M` queues as round-robin-scheduled priority levels and pops the next value in
+    /// turn, for fairness across queues rather than always favoring low-numbered ones.
+    ///
+    /// Scans starting right after the queue served by the previous call, wrapping around, and
+    /// returns the first non-empty queue's front value together with its id. Returns `None` once
+    /// every queue has been tried and found empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::MultiStackQueue;
+    ///
+    /// let mut msq: MultiStackQueue<usize, 4, 3> = MultiStackQueue::new();
+    /// msq.push(0, 10).unwrap();
+    /// msq.push(2, 20).unwrap();
+    ///
+    /// // The cursor starts as if queue 0 was last served, so queue 2 is reached first.
+    /// assert_eq!(msq.pop_round_robin(), Some((2, 20)));
+    /// assert_eq!(msq.pop_round_robin(), Some((0, 10)));
+    /// assert_eq!(msq.pop_round_robin(), None);
+    /// ```
+    ///
+    pub fn pop_round_robin(&mut self) -> Option<(usize, T)> {
+        for offset in 1..=M {
+            let id = (self.rr_cursor + offset) % M;
+            if let Ok(value) = self.pop(id) {
+                self.rr_cursor = id;
+                return Some((id, value));
+            }
+        }
+        None
+    }
+    /// Treats queue `0` as the highest priority level and pops from the lowest-numbered
+    /// non-empty queue, unlike [`MultiStackQueue::pop_round_robin`] which is fair across calls.
+    ///
+    /// Returns `None` once every queue has been tried and found empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::MultiStackQueue;
+    ///
+    /// let mut msq: MultiStackQueue<usize, 4, 3> = MultiStackQueue::new();
+    /// msq.push(2, 20).unwrap();
+    /// msq.push(0, 10).unwrap();
+    ///
+    /// assert_eq!(msq.pop_highest_priority(), Some((0, 10)));
+    /// assert_eq!(msq.pop_highest_priority(), Some((2, 20)));
+    /// assert_eq!(msq.pop_highest_priority(), None);
+    /// ```
+    ///
+    pub fn pop_highest_priority(&mut self) -> Option<(usize, T)> {
+        for id in 0..M {
+            if let Ok(value) = self.pop(id) {
+                return Some((id, value));
             }
-            Ok(res)
         }
+        None
     }
     /// Returns whether a particular queue is empty
     /// # Examples
@@ -208,16 +497,331 @@ impl<T: Copy, const N: usize, const M: usize> MultiStackQueue<T, N, M> {
     /// ```
     ///
     pub fn is_full(&self, id: usize) -> bool {
-        !self.empty[id] && self.ins[id] == self.outs[id]
+        let h = I::load(&self.heads[id], Ordering::Acquire);
+        let t = I::load(&self.tails[id], Ordering::Acquire);
+        (t + 2 * N - h) % (2 * N) == N
     }
     pub fn is_empty(&self, id: usize) -> bool {
-        self.empty[id]
+        let h = I::load(&self.heads[id], Ordering::Acquire);
+        let t = I::load(&self.tails[id], Ordering::Acquire);
+        h == t
+    }
+    /// Splits the multiqueue into a [`Producer`] and a [`Consumer`] half, mirroring
+    /// `heapless`'s `spsc::Queue::split`.
+    ///
+    /// The `Producer` may only `enqueue` and the `Consumer` may only `dequeue`, so handing one
+    /// half to an interrupt handler and the other to the main loop gives a wait-free,
+    /// mutex-free channel for each queue in the multiqueue: as long as at most one producer and
+    /// one consumer ever operate on a given `id` at a time, `enqueue`/`dequeue` never race.
+    pub fn split(&mut self) -> (Producer<'_, T, N, M, I>, Consumer<'_, T, N, M, I>) {
+        let data = self.data.get();
+        let heads: *const [I::Atomic; M] = &self.heads;
+        let tails: *const [I::Atomic; M] = &self.tails;
+        (
+            Producer {
+                data,
+                heads,
+                tails,
+                _marker: PhantomData,
+            },
+            Consumer {
+                data,
+                heads,
+                tails,
+                _marker: PhantomData,
+            },
+        )
+    }
+    /// Returns an iterator over queue `id`'s elements, from front to back, without popping them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::MultiStackQueue;
+    ///
+    /// let mut msq: MultiStackQueue<usize, 4, 2> = MultiStackQueue::new();
+    /// msq.push(0, 1).unwrap();
+    /// msq.push(0, 2).unwrap();
+    ///
+    /// assert_eq!(msq.iter(0).copied().collect::<Vec<_>>(), vec![1, 2]);
+    /// assert_eq!(msq.pop(0).unwrap(), 1);
+    /// ```
+    ///
+    pub fn iter(&self, id: usize) -> Iter<'_, T, N> {
+        let h = I::load(&self.heads[id], Ordering::Acquire);
+        let t = I::load(&self.tails[id], Ordering::Acquire);
+        let lap = 2 * N;
+        // SAFETY: `&self` guarantees no concurrent `&mut` access to `data` exists.
+        let queue: *const [MaybeUninit<T>; N] = unsafe { &(*self.data.get())[id] };
+        Iter {
+            data: queue,
+            head: h % N,
+            remaining: (t + lap - h) % lap,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns a mutable iterator over queue `id`'s elements, from front to back, without
+    /// popping them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::MultiStackQueue;
+    ///
+    /// let mut msq: MultiStackQueue<usize, 4, 2> = MultiStackQueue::new();
+    /// msq.push(0, 1).unwrap();
+    /// msq.push(0, 2).unwrap();
+    ///
+    /// for value in msq.iter_mut(0) {
+    ///     *value *= 10;
+    /// }
+    /// assert_eq!(msq.iter(0).copied().collect::<Vec<_>>(), vec![10, 20]);
+    /// ```
+    ///
+    pub fn iter_mut(&mut self, id: usize) -> IterMut<'_, T, N> {
+        let h = I::load(&self.heads[id], Ordering::Acquire);
+        let t = I::load(&self.tails[id], Ordering::Acquire);
+        let lap = 2 * N;
+        // SAFETY: `&mut self` guarantees exclusive access to `data`.
+        let queue: *mut [MaybeUninit<T>; N] = unsafe { &mut (*self.data.get())[id] };
+        IterMut {
+            data: queue,
+            head: h % N,
+            remaining: (t + lap - h) % lap,
+            _marker: PhantomData,
+        }
+    }
+    /// Removes and returns every element of queue `id`, from front to back.
+    ///
+    /// Queue `id` is left empty as soon as `drain` is called, even if the returned iterator is
+    /// dropped before being fully consumed: any elements not yielded are dropped in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use multi_stack_queue::MultiStackQueue;
+    ///
+    /// let mut msq: MultiStackQueue<usize, 4, 2> = MultiStackQueue::new();
+    /// msq.push(0, 1).unwrap();
+    /// msq.push(0, 2).unwrap();
+    ///
+    /// assert_eq!(msq.drain(0).collect::<Vec<_>>(), vec![1, 2]);
+    /// assert!(msq.is_empty(0));
+    /// ```
+    ///
+    pub fn drain(&mut self, id: usize) -> Drain<'_, T, N> {
+        let h = I::load(&self.heads[id], Ordering::Acquire);
+        let t = I::load(&self.tails[id], Ordering::Acquire);
+        let lap = 2 * N;
+        let remaining = (t + lap - h) % lap;
+        // The queue is emptied up front: `head` keeps its value and `tail` is pulled back to
+        // match it, so the queue is consistently empty regardless of how much of the returned
+        // iterator the caller actually drives.
+        I::store(&self.tails[id], h, Ordering::Release);
+        // SAFETY: `&mut self` guarantees exclusive access to `data`.
+        let queue: *mut [MaybeUninit<T>; N] = unsafe { &mut (*self.data.get())[id] };
+        Drain {
+            data: queue,
+            head: h % N,
+            remaining,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, const N: usize, const M: usize> MultiStackQueue<T, N, M, u8> {
+    /// Returns a new empty multiqueue with `u8` head/tail counters, avoiding a turbofish.
+    ///
+    /// Requires `N <= 128`, since each queue's head/tail counters range over `0..2*N`.
+    pub fn new_u8() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize, const M: usize> MultiStackQueue<T, N, M, u16> {
+    /// Returns a new empty multiqueue with `u16` head/tail counters, avoiding a turbofish.
+    ///
+    /// Requires `N <= 32768`, since each queue's head/tail counters range over `0..2*N`.
+    pub fn new_u16() -> Self {
+        Self::new()
+    }
+}
+
+/// The producing half of a [`MultiStackQueue`] obtained via [`MultiStackQueue::split`].
+///
+/// Only able to `enqueue` onto any of the `M` queues; meant to be handed to the single writer
+/// side of each queue (e.g. the interrupt handler spawning new threads into a priority level).
+pub struct Producer<'a, T, const N: usize, const M: usize, I: IndexWidth = usize> {
+    data: *mut [[MaybeUninit<T>; N]; M],
+    heads: *const [I::Atomic; M],
+    tails: *const [I::Atomic; M],
+    _marker: PhantomData<&'a MultiStackQueue<T, N, M, I>>,
+}
+
+// SAFETY: `Producer` only ever writes to the slot at `tail`, which `Consumer` never touches,
+// so moving it to another thread is sound as long as `T` itself is `Send`.
+unsafe impl<'a, T: Send, const N: usize, const M: usize, I: IndexWidth> Send
+    for Producer<'a, T, N, M, I>
+{
+}
+
+impl<'a, T, const N: usize, const M: usize, I: IndexWidth> Producer<'a, T, N, M, I> {
+    /// Appends a value to queue `id`. See [`MultiStackQueue::push`].
+    pub fn enqueue(&mut self, id: usize, value: T) -> Result<(), MSQError> {
+        if id >= M {
+            return Err(MSQError::QueueIndexOutOfBounds);
+        }
+        // SAFETY: `heads`/`tails` point at the multiqueue's atomic arrays, which outlive this
+        // `Producer` for `'a`.
+        let (heads, tails) = unsafe { (&*self.heads, &*self.tails) };
+        enqueue_at::<T, I, N, M>(self.data, &heads[id], &tails[id], id, value).map_err(|(e, _)| e)
+    }
+}
+
+/// The consuming half of a [`MultiStackQueue`] obtained via [`MultiStackQueue::split`].
+///
+/// Only able to `dequeue` from any of the `M` queues; meant to be handed to the single reader
+/// side of each queue (e.g. the scheduler's main loop).
+pub struct Consumer<'a, T, const N: usize, const M: usize, I: IndexWidth = usize> {
+    data: *mut [[MaybeUninit<T>; N]; M],
+    heads: *const [I::Atomic; M],
+    tails: *const [I::Atomic; M],
+    _marker: PhantomData<&'a MultiStackQueue<T, N, M, I>>,
+}
+
+// SAFETY: `Consumer` only ever reads (and takes) the slot at `head`, which `Producer` never
+// touches, so moving it to another thread is sound as long as `T` itself is `Send`.
+unsafe impl<'a, T: Send, const N: usize, const M: usize, I: IndexWidth> Send
+    for Consumer<'a, T, N, M, I>
+{
+}
+
+impl<'a, T, const N: usize, const M: usize, I: IndexWidth> Consumer<'a, T, N, M, I> {
+    /// Removes and returns the front value of queue `id`. See [`MultiStackQueue::pop`].
+    pub fn dequeue(&mut self, id: usize) -> Result<T, MSQError> {
+        if id >= M {
+            return Err(MSQError::QueueIndexOutOfBounds);
+        }
+        // SAFETY: `heads`/`tails` point at the multiqueue's atomic arrays, which outlive this
+        // `Consumer` for `'a`.
+        let (heads, tails) = unsafe { (&*self.heads, &*self.tails) };
+        dequeue_at::<T, I, N, M>(self.data, &heads[id], &tails[id], id)
+    }
+}
+
+impl<T, const N: usize, const M: usize, I: IndexWidth> Drop for MultiStackQueue<T, N, M, I> {
+    fn drop(&mut self) {
+        let data = self.data.get();
+        let lap = 2 * N;
+        for id in 0..M {
+            let h = I::load(&self.heads[id], Ordering::Relaxed);
+            let t = I::load(&self.tails[id], Ordering::Relaxed);
+            let len = (t + lap - h) % lap;
+            for offset in 0..len {
+                let idx = (h + offset) % N;
+                // SAFETY: every slot in `h..t` (mod N) was written by `enqueue_at` and not yet
+                // moved out by `dequeue_at`, so it is exactly the set of initialized, still-live
+                // elements; each is visited exactly once.
+                unsafe {
+                    (*data)[id][idx].assume_init_drop();
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over a single queue's elements, returned by [`MultiStackQueue::iter`].
+pub struct Iter<'a, T, const N: usize> {
+    data: *const [MaybeUninit<T>; N],
+    head: usize,
+    remaining: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let idx = self.head;
+        self.head = (self.head + 1) % N;
+        self.remaining -= 1;
+        // SAFETY: every slot visited here lies within the live range captured when the iterator
+        // was created, and `&'a T` can't outlive the `&self` borrow that produced it.
+        Some(unsafe { (*self.data)[idx].assume_init_ref() })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Mutable iterator over a single queue's elements, returned by [`MultiStackQueue::iter_mut`].
+pub struct IterMut<'a, T, const N: usize> {
+    data: *mut [MaybeUninit<T>; N],
+    head: usize,
+    remaining: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, const N: usize> Iterator for IterMut<'a, T, N> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let idx = self.head;
+        self.head = (self.head + 1) % N;
+        self.remaining -= 1;
+        // SAFETY: every slot visited here lies within the live range captured when the iterator
+        // was created, each is visited at most once, and `&'a mut T` can't outlive the `&mut
+        // self` borrow that produced it.
+        Some(unsafe { (*self.data)[idx].assume_init_mut() })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Draining iterator over a single queue's elements, returned by [`MultiStackQueue::drain`].
+///
+/// The source queue is already empty by the time this is returned; dropping it before it is
+/// fully consumed drops the remaining elements in place rather than leaking them.
+pub struct Drain<'a, T, const N: usize> {
+    data: *mut [MaybeUninit<T>; N],
+    head: usize,
+    remaining: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, const N: usize> Iterator for Drain<'a, T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let idx = self.head;
+        self.head = (self.head + 1) % N;
+        self.remaining -= 1;
+        // SAFETY: every slot visited here lies within the range `MultiStackQueue::drain` handed
+        // off, each is visited (and thus moved out) at most once, and the source queue was
+        // already marked empty so nothing else can observe or re-read this slot.
+        Some(unsafe { (*self.data)[idx].assume_init_read() })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, const N: usize> Drop for Drain<'a, T, N> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::MultiStackQueue;
+    use crate::{MSQError, MultiStackQueue};
     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     struct TestStruct {
         a: usize,
@@ -289,4 +893,223 @@ mod tests {
         assert_eq!(a.pop(0).unwrap(), 1);
         assert_eq!(a.pop(0).unwrap(), 2);
     }
+
+    #[test]
+    fn push_with_policy_cascade_down() {
+        use crate::OverflowPolicy;
+
+        let mut a: MultiStackQueue<usize, 1, 4> = MultiStackQueue::new();
+        a.push(0, 1).unwrap();
+        let landed = a.push_with_policy(0, 2, OverflowPolicy::CascadeDown).unwrap();
+        assert_eq!(landed, 1);
+        assert_eq!(a.pop(1).unwrap(), 2);
+    }
+
+    #[test]
+    fn push_with_policy_cascade_down_all_full() {
+        use crate::OverflowPolicy;
+
+        let mut a: MultiStackQueue<usize, 1, 2> = MultiStackQueue::new();
+        a.push(0, 1).unwrap();
+        a.push(1, 2).unwrap();
+        assert_eq!(
+            a.push_with_policy(0, 3, OverflowPolicy::CascadeDown),
+            Err(MSQError::QueueFull)
+        );
+    }
+
+    #[test]
+    fn non_copy_type() {
+        let mut a: MultiStackQueue<String, 4, 2> = MultiStackQueue::new();
+        a.push(0, String::from("hello")).unwrap();
+        a.push(0, String::from("world")).unwrap();
+        assert_eq!(a.pop(0).unwrap(), "hello");
+        assert_eq!(a.pop(0).unwrap(), "world");
+    }
+
+    #[test]
+    fn drops_remaining_elements() {
+        use std::sync::atomic::{AtomicUsize as Counter, Ordering as CounterOrdering};
+
+        static DROPS: Counter = Counter::new(0);
+
+        struct DropTracked;
+
+        impl Drop for DropTracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, CounterOrdering::Relaxed);
+            }
+        }
+
+        {
+            let mut a: MultiStackQueue<DropTracked, 4, 2> = MultiStackQueue::new();
+            a.push(0, DropTracked).unwrap();
+            a.push(0, DropTracked).unwrap();
+            a.push(1, DropTracked).unwrap();
+            // One element is popped (and thus dropped) up front, the rest must be dropped
+            // when `a` itself goes out of scope below.
+            a.pop(0).unwrap();
+        }
+
+        assert_eq!(DROPS.load(CounterOrdering::Relaxed), 3);
+    }
+
+    #[test]
+    fn split_producer_consumer() {
+        let mut a: MultiStackQueue<usize, 16, 32> = MultiStackQueue::new();
+        let (mut prod, mut cons) = a.split();
+        prod.enqueue(3, 42).unwrap();
+        assert_eq!(cons.dequeue(3).unwrap(), 42);
+        assert!(cons.dequeue(3).is_err());
+    }
+
+    #[test]
+    fn split_across_threads() {
+        use std::thread;
+
+        let mut a: MultiStackQueue<usize, 64, 4> = MultiStackQueue::new();
+        let (mut prod, mut cons) = a.split();
+
+        thread::scope(|s| {
+            s.spawn(move || {
+                for i in 0..64 {
+                    while prod.enqueue(1, i).is_err() {}
+                }
+            });
+            s.spawn(move || {
+                for i in 0..64 {
+                    let mut v = cons.dequeue(1);
+                    while v.is_err() {
+                        v = cons.dequeue(1);
+                    }
+                    assert_eq!(v.unwrap(), i);
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn new_u8_small_index_width() {
+        let mut a: MultiStackQueue<usize, 4, 8, u8> = MultiStackQueue::new_u8();
+        a.push(2, 99).unwrap();
+        assert_eq!(a.pop(2).unwrap(), 99);
+    }
+
+    #[test]
+    fn new_u16_fill_and_overflow() {
+        let mut a: MultiStackQueue<usize, 4, 8, u16> = MultiStackQueue::new_u16();
+        for i in 0..4 {
+            a.push(0, i).unwrap();
+        }
+        assert!(a.is_full(0));
+        assert_eq!(a.push(0, 42), Err(MSQError::QueueFull));
+    }
+
+    #[test]
+    fn pop_round_robin_is_fair() {
+        let mut a: MultiStackQueue<usize, 4, 3> = MultiStackQueue::new();
+        a.push(0, 10).unwrap();
+        a.push(1, 11).unwrap();
+        a.push(2, 12).unwrap();
+
+        // The cursor starts as if queue 0 was last served, so the first call scans 1, 2, 0.
+        assert_eq!(a.pop_round_robin(), Some((1, 11)));
+        assert_eq!(a.pop_round_robin(), Some((2, 12)));
+        assert_eq!(a.pop_round_robin(), Some((0, 10)));
+        assert_eq!(a.pop_round_robin(), None);
+    }
+
+    #[test]
+    fn pop_round_robin_resumes_after_cursor() {
+        let mut a: MultiStackQueue<usize, 4, 3> = MultiStackQueue::new();
+        a.push(1, 10).unwrap();
+        assert_eq!(a.pop_round_robin(), Some((1, 10)));
+
+        // Cursor now sits on queue 1, so the next call should skip straight to queue 2,
+        // not restart from queue 0.
+        a.push(2, 20).unwrap();
+        a.push(0, 30).unwrap();
+        assert_eq!(a.pop_round_robin(), Some((2, 20)));
+        assert_eq!(a.pop_round_robin(), Some((0, 30)));
+    }
+
+    #[test]
+    fn pop_highest_priority_prefers_low_ids() {
+        let mut a: MultiStackQueue<usize, 4, 3> = MultiStackQueue::new();
+        a.push(2, 20).unwrap();
+        a.push(1, 10).unwrap();
+
+        assert_eq!(a.pop_highest_priority(), Some((1, 10)));
+        assert_eq!(a.pop_highest_priority(), Some((2, 20)));
+        assert_eq!(a.pop_highest_priority(), None);
+    }
+
+    #[test]
+    fn iter_does_not_consume() {
+        let mut a: MultiStackQueue<usize, 4, 2> = MultiStackQueue::new();
+        a.push(0, 1).unwrap();
+        a.push(0, 2).unwrap();
+        a.push(0, 3).unwrap();
+        a.pop(0).unwrap();
+        a.push(0, 4).unwrap();
+
+        assert_eq!(a.iter(0).copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(a.iter(0).copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(a.pop(0).unwrap(), 2);
+    }
+
+    #[test]
+    fn iter_mut_updates_in_place() {
+        let mut a: MultiStackQueue<usize, 4, 1> = MultiStackQueue::new();
+        a.push(0, 1).unwrap();
+        a.push(0, 2).unwrap();
+
+        for value in a.iter_mut(0) {
+            *value *= 10;
+        }
+
+        assert_eq!(a.iter(0).copied().collect::<Vec<_>>(), vec![10, 20]);
+    }
+
+    #[test]
+    fn drain_empties_the_queue_and_yields_in_order() {
+        let mut a: MultiStackQueue<usize, 4, 1> = MultiStackQueue::new();
+        a.push(0, 1).unwrap();
+        a.push(0, 2).unwrap();
+        a.push(0, 3).unwrap();
+
+        assert_eq!(a.drain(0).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(a.is_empty(0));
+        a.push(0, 4).unwrap();
+        assert_eq!(a.pop(0).unwrap(), 4);
+    }
+
+    #[test]
+    fn drain_dropped_early_still_empties_and_drops_the_rest() {
+        use std::sync::atomic::{AtomicUsize as Counter, Ordering as CounterOrdering};
+
+        static DROPS: Counter = Counter::new(0);
+
+        struct DropTracked;
+
+        impl Drop for DropTracked {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, CounterOrdering::Relaxed);
+            }
+        }
+
+        let mut a: MultiStackQueue<DropTracked, 4, 1> = MultiStackQueue::new();
+        a.push(0, DropTracked).unwrap();
+        a.push(0, DropTracked).unwrap();
+        a.push(0, DropTracked).unwrap();
+
+        {
+            let mut drain = a.drain(0);
+            drain.next().unwrap();
+            // Drop the rest without visiting them.
+        }
+
+        assert!(a.is_empty(0));
+        assert_eq!(DROPS.load(CounterOrdering::Relaxed), 3);
+    }
 }